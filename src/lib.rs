@@ -7,21 +7,23 @@
 //! This crate uses features of `alloc` crate, so you have to extern `alloc` crate. This means you
 //! have to define your own heap allocator.
 //!
-//! Currently this crate only supports 24 or 32 bits color of BGR order.
+//! This crate supports several VRAM pixel layouts through the [`PixelFormat`] enum, so it is not
+//! limited to 24/32-bit BGR framebuffers.
 //!
 //! # Examples
 //!
 //! ```rust
-//! use screen_layer::{self, Layer, Vec2, RGB8};
+//! use screen_layer::{self, Layer, PixelFormat, Vec2, RGBA8};
 //!
 //! const SCREEN_WIDTH: u32 = 10;
 //! const SCREEN_HEIGHT: u32 = 10;
+//! const FORMAT: PixelFormat = PixelFormat::Bgra8888;
 //! const BPP: u32 = 32;
 //!
 //! let mut pseudo_vram = [0u8; (SCREEN_WIDTH * SCREEN_HEIGHT * BPP / 8) as usize];
 //! let ptr = pseudo_vram.as_ptr() as usize;
 //! let mut controller =
-//!     unsafe { screen_layer::Controller::new(Vec2::new(SCREEN_WIDTH, SCREEN_HEIGHT), BPP, ptr) };
+//!     unsafe { screen_layer::Controller::new(Vec2::new(SCREEN_WIDTH, SCREEN_HEIGHT), FORMAT, ptr) };
 //!
 //! const LAYER_WIDTH: u32 = 5;
 //! const LAYER_HEIGHT: u32 = 5;
@@ -31,10 +33,11 @@
 //! controller
 //!     .edit_layer(id, |layer: &mut Layer| {
 //!         for i in 0..LAYER_WIDTH {
-//!             layer[i as usize][i as usize] = Some(RGB8::new(0, 255, 0));
+//!             layer[i as usize][i as usize] = Some(RGBA8::new(0, 255, 0, 255));
 //!         }
 //!     })
 //!     .unwrap();
+//! controller.flush();
 //!
 //! for i in 0..LAYER_WIDTH {
 //!     assert_eq!(pseudo_vram[(BPP / 8 * (i * SCREEN_WIDTH + i)) as usize], 0);
@@ -42,7 +45,8 @@
 //!     assert_eq!(pseudo_vram[(BPP / 8 * (i * SCREEN_WIDTH + i) + 2) as usize], 0);
 //! }
 //!
-//! controller.set_pixel(id, Vec2::one(), Some(RGB8::new(255, 0, 0)));
+//! controller.set_pixel(id, Vec2::one(), Some(RGBA8::new(255, 0, 0, 255)));
+//! controller.flush();
 //! assert_eq!(pseudo_vram[(BPP / 8 * (1 * SCREEN_WIDTH + 1)) as usize], 0);
 //! assert_eq!(pseudo_vram[(BPP / 8 * (1 * SCREEN_WIDTH + 1) + 1) as usize], 0);
 //! assert_eq!(pseudo_vram[(BPP / 8 * (1 * SCREEN_WIDTH + 1) + 2) as usize], 255);
@@ -58,6 +62,7 @@ use {
     alloc::vec::Vec,
     core::{
         convert::{TryFrom, TryInto},
+        mem,
         mem::size_of,
         ops::{Index, IndexMut},
         ptr,
@@ -68,6 +73,9 @@ use {
 /// This type is used to represent color of each pixels.
 pub use rgb::RGB8;
 
+/// This type is used to represent color with an alpha channel of each pixels.
+pub use rgb::RGBA8;
+
 /// This type is used to represent the coordinate, and width and height of a layer.
 pub use vek::Vec2;
 
@@ -76,6 +84,8 @@ pub use vek::Vec2;
 pub struct Controller {
     vram: Vram,
     collection: Vec<Layer>,
+    back_buffer: Vec<RGB8>,
+    dirty_rects: Vec<(Vec2<i32>, Vec2<i32>)>,
 }
 
 impl Controller {
@@ -90,12 +100,15 @@ impl Controller {
     /// value than the actual one.
     pub unsafe fn new(
         resolution: Vec2<u32>,
-        bits_per_pixel: u32,
+        format: PixelFormat,
         base_addr_of_vram: usize,
     ) -> Self {
+        let num_pixels = (resolution.x * resolution.y) as usize;
         Self {
-            vram: Vram::new(resolution, bits_per_pixel, base_addr_of_vram),
+            vram: Vram::new(resolution, format, base_addr_of_vram),
             collection: Vec::new(),
+            back_buffer: vec![RGB8::new(0, 0, 0); num_pixels],
+            dirty_rects: Vec::new(),
         }
     }
 
@@ -105,13 +118,13 @@ impl Controller {
     ///
     /// Added layer comes to the front. All layers behind the one will be hidden.
     ///
-    /// After adding a layer, layers will be redrawn.
+    /// This marks the layer's region dirty; call [`Self::flush`] to make it visible.
     pub fn add_layer(&mut self, layer: Layer) -> Id {
         let id = layer.id;
         let top_left = layer.top_left;
         let len = layer.len;
         self.collection.push(layer);
-        self.redraw(top_left, len);
+        self.composite(top_left, len);
         id
     }
 
@@ -120,8 +133,8 @@ impl Controller {
     /// You can edit a layer by indexing `Layer` type. For more information, see the description of
     /// `Index` implementation of `Layer` type.
     ///
-    /// After editing, layers will be redrawn. This may take time if the layer is large. In such
-    /// cases, use [`set_pixel`] instead.
+    /// This marks the layer's region dirty; call [`Self::flush`] to make it visible. Compositing
+    /// may take time if the layer is large. In such cases, use [`set_pixel`] instead.
     pub fn edit_layer<T>(&mut self, id: Id, f: T) -> Result<(), Error>
     where
         T: Fn(&mut Layer),
@@ -130,26 +143,30 @@ impl Controller {
         let layer_top_left = layer.top_left;
         let layer_len = layer.len;
         f(layer);
-        self.redraw(layer_top_left, layer_len);
+        self.composite(layer_top_left, layer_len);
         Ok(())
     }
 
     /// Set a color on pixel.
     ///
-    /// `coord` is the coordinate of the relative position from the top-left of the layer. If `color` is `None`, the pixel is transparent.
+    /// `coord` is the coordinate of the relative position from the top-left of the layer's buffer.
+    /// If `color` is `None`, the pixel is transparent.
     ///
-    /// After editing, only the edited pixel will be redrawn.
+    /// This marks only the edited pixel dirty (or, for a layer created with
+    /// [`Layer::new_scaled`], the on-screen block it is scaled up to); call [`Self::flush`] to
+    /// make it visible.
     pub fn set_pixel(
         &mut self,
         id: Id,
         coord: Vec2<u32>,
-        color: Option<RGB8>,
+        color: Option<RGBA8>,
     ) -> Result<(), Error> {
         let layer = self.id_to_layer(id)?;
         let layer_top_left = layer.top_left;
+        let scale = layer.scale;
         layer[usize::try_from(coord.y).unwrap()][usize::try_from(coord.x).unwrap()] = color;
 
-        self.redraw(layer_top_left + coord.as_(), Vec2::one());
+        self.composite(layer_top_left + (coord * scale).as_(), scale);
         Ok(())
     }
 
@@ -158,18 +175,186 @@ impl Controller {
     /// The value of `new_top_left` can be negative, or larger than screen resolution. In such
     /// cases, any part of the layer that extends outside the screen will not be drawn.
     ///
-    /// After sliding, layers will be redrawn.
+    /// This marks both the old and new regions the layer covers dirty; call [`Self::flush`] to
+    /// make the change visible.
     pub fn slide_layer(&mut self, id: Id, new_top_left: Vec2<i32>) -> Result<(), Error> {
         let layer = self.id_to_layer(id)?;
         let old_top_left = layer.top_left;
         let layer_len = layer.len;
         layer.slide(new_top_left);
-        self.redraw(old_top_left, layer_len);
-        self.redraw(new_top_left, layer_len);
+        self.composite(old_top_left, layer_len);
+        self.composite(new_top_left, layer_len);
+        Ok(())
+    }
+
+    /// Blurs a layer's pixels in place with a separable Gaussian blur, then marks the affected
+    /// region dirty.
+    ///
+    /// `sigma` is the standard deviation of the Gaussian; larger values blur more heavily. Call
+    /// [`Self::flush`] to make the change visible.
+    pub fn blur_layer(&mut self, id: Id, sigma: f32) -> Result<(), Error> {
+        let layer = self.id_to_layer(id)?;
+        let layer_top_left = layer.top_left;
+        let layer_len = layer.len;
+        layer.blur(sigma);
+        self.composite(layer_top_left, layer_len);
+        Ok(())
+    }
+
+    /// Draws `text` into a layer using a bitmap font.
+    ///
+    /// `origin` is the top-left corner of the first glyph, relative to the layer. Only the set
+    /// bits of each glyph are drawn, in `fg`; every other pixel of the layer is left untouched.
+    /// `'\n'` moves the cursor back under `origin.x` and down one glyph row; characters the font
+    /// has no glyph for are skipped.
+    ///
+    /// This marks the affected layer region dirty; call [`Self::flush`] to make it visible.
+    pub fn draw_text(
+        &mut self,
+        id: Id,
+        origin: Vec2<u32>,
+        text: &str,
+        fg: RGB8,
+        font: &Font,
+    ) -> Result<(), Error> {
+        let layer = self.id_to_layer(id)?;
+        let layer_top_left = layer.top_left;
+        let layer_len = layer.len;
+        layer.draw_text(origin, text, fg, font);
+        self.composite(layer_top_left, layer_len);
+        Ok(())
+    }
+
+    /// Copies a sub-rectangle of one layer's buffer into another.
+    ///
+    /// `src_top_left` and `src_len` describe the sub-rectangle of the `src_id` layer to copy,
+    /// clipped to its bounds; it is copied to `dst_id` at `dst_pos`. Transparent (`None`) source
+    /// pixels are skipped, leaving the destination pixel untouched.
+    ///
+    /// This marks the destination region dirty; call [`Self::flush`] to make it visible.
+    pub fn blit(
+        &mut self,
+        dst_id: Id,
+        dst_pos: Vec2<u32>,
+        src_id: Id,
+        src_top_left: Vec2<u32>,
+        src_len: Vec2<u32>,
+    ) -> Result<(), Error> {
+        let src_index = self.id_to_index(src_id)?;
+        let src_layer = &self.collection[src_index];
+        let src_bottom_right = Vec2::min(src_top_left + src_len, src_layer.buf_len());
+        let copied: Vec<Vec<Option<RGBA8>>> = (src_top_left.y..src_bottom_right.y)
+            .map(|y| {
+                (src_top_left.x..src_bottom_right.x)
+                    .map(|x| src_layer.buf[y as usize][x as usize])
+                    .collect()
+            })
+            .collect();
+
+        let dst_layer = self.id_to_layer(dst_id)?;
+        let dst_buf_len = dst_layer.buf_len();
+        for (row_offset, row) in copied.iter().enumerate() {
+            for (col_offset, &pixel) in row.iter().enumerate() {
+                if pixel.is_none() {
+                    continue;
+                }
+
+                let (x, y) = (dst_pos.x + col_offset as u32, dst_pos.y + row_offset as u32);
+                if x < dst_buf_len.x && y < dst_buf_len.y {
+                    dst_layer.buf[y as usize][x as usize] = pixel;
+                }
+            }
+        }
+        let dst_top_left = dst_layer.top_left;
+        let dst_len = dst_layer.len;
+
+        self.composite(dst_top_left, dst_len);
         Ok(())
     }
 
-    fn redraw(&self, mut vram_top_left: Vec2<i32>, len: Vec2<u32>) {
+    /// Removes a layer.
+    ///
+    /// This marks the region the layer used to occupy dirty, so that [`Self::flush`] redraws it
+    /// with the layers that were behind it.
+    pub fn remove_layer(&mut self, id: Id) -> Result<Layer, Error> {
+        let index = self.id_to_index(id)?;
+        let layer = self.collection.remove(index);
+        self.composite(layer.top_left, layer.len);
+        Ok(layer)
+    }
+
+    /// Moves a layer to the front, in front of all other layers.
+    ///
+    /// This marks the layer's region dirty; call [`Self::flush`] to make the change visible.
+    pub fn move_to_front(&mut self, id: Id) -> Result<(), Error> {
+        let index = self.id_to_index(id)?;
+        let layer = self.collection.remove(index);
+        let (top_left, len) = (layer.top_left, layer.len);
+        self.collection.push(layer);
+        self.composite(top_left, len);
+        Ok(())
+    }
+
+    /// Moves a layer to the back, behind all other layers.
+    ///
+    /// This marks the layer's region dirty; call [`Self::flush`] to make the change visible.
+    pub fn move_to_back(&mut self, id: Id) -> Result<(), Error> {
+        let index = self.id_to_index(id)?;
+        let layer = self.collection.remove(index);
+        let (top_left, len) = (layer.top_left, layer.len);
+        self.collection.insert(0, layer);
+        self.composite(top_left, len);
+        Ok(())
+    }
+
+    /// Raises a layer one step, swapping it with the layer in front of it.
+    ///
+    /// Does nothing if the layer is already at the front. Otherwise, this marks the layer's
+    /// region dirty; call [`Self::flush`] to make the change visible.
+    pub fn raise(&mut self, id: Id) -> Result<(), Error> {
+        let index = self.id_to_index(id)?;
+        if index + 1 < self.collection.len() {
+            self.collection.swap(index, index + 1);
+            let moved = &self.collection[index + 1];
+            let (top_left, len) = (moved.top_left, moved.len);
+            self.composite(top_left, len);
+        }
+        Ok(())
+    }
+
+    /// Lowers a layer one step, swapping it with the layer behind it.
+    ///
+    /// Does nothing if the layer is already at the back. Otherwise, this marks the layer's region
+    /// dirty; call [`Self::flush`] to make the change visible.
+    pub fn lower(&mut self, id: Id) -> Result<(), Error> {
+        let index = self.id_to_index(id)?;
+        if index > 0 {
+            self.collection.swap(index, index - 1);
+            let moved = &self.collection[index - 1];
+            let (top_left, len) = (moved.top_left, moved.len);
+            self.composite(top_left, len);
+        }
+        Ok(())
+    }
+
+    /// Moves a layer to the given z-index, counted from the back (`0`) to the front (the number
+    /// of layers minus one).
+    ///
+    /// `z_index` is clamped to the number of remaining layers if it is too large. This marks the
+    /// layer's region dirty; call [`Self::flush`] to make the change visible.
+    pub fn set_z_index(&mut self, id: Id, z_index: usize) -> Result<(), Error> {
+        let index = self.id_to_index(id)?;
+        let layer = self.collection.remove(index);
+        let (top_left, len) = (layer.top_left, layer.len);
+        let z_index = z_index.min(self.collection.len());
+        self.collection.insert(z_index, layer);
+        self.composite(top_left, len);
+        Ok(())
+    }
+
+    /// Composites the layers covering the given VRAM region into the back buffer, and records
+    /// the (clipped) region as dirty so that [`Self::flush`] copies it to VRAM.
+    fn composite(&mut self, mut vram_top_left: Vec2<i32>, len: Vec2<u32>) {
         vram_top_left = Vec2::<i32>::max(
             Vec2::min(vram_top_left, self.vram.resolution.as_()),
             Vec2::zero(),
@@ -181,41 +366,107 @@ impl Controller {
             Vec2::zero(),
         );
 
-        for layer in &self.collection {
-            let layer_bottom_right = layer.top_left + layer.len.as_();
+        if vram_top_left.x >= vram_bottom_right.x || vram_top_left.y >= vram_bottom_right.y {
+            return;
+        }
+
+        for y in vram_top_left.y..vram_bottom_right.y {
+            for x in vram_top_left.x..vram_bottom_right.x {
+                let mut out = RGB8::new(0, 0, 0);
 
-            let top_left =
-                Vec2::<i32>::min(Vec2::max(vram_top_left, layer.top_left), layer_bottom_right);
-            let bottom_right =
-                Vec2::<i32>::max(top_left, Vec2::min(vram_bottom_right, layer_bottom_right));
+                for layer in &self.collection {
+                    if let Some(src) = layer.pixel_at(Vec2::new(x, y)) {
+                        out = layer.blend_mode.composite(src, out);
+                    }
+                }
+
+                self.back_buffer[(y as u32 * self.vram.resolution.x + x as u32) as usize] = out;
+            }
+        }
 
+        self.dirty_rects.push((vram_top_left, vram_bottom_right));
+    }
+
+    /// Copies every pixel covered by the accumulated dirty rectangles from the back buffer to
+    /// VRAM, then clears the dirty rectangle list.
+    ///
+    /// None of the other methods on this type write to VRAM themselves; they only composite into
+    /// the back buffer and record the region they touched as dirty. Call this after one or more
+    /// of them to make the accumulated changes visible. Overlapping or adjacent dirty rectangles
+    /// are merged first, so a span of VRAM touched by several operations is only written once.
+    pub fn flush(&mut self) {
+        let dirty_rects = mem::take(&mut self.dirty_rects);
+        for (top_left, bottom_right) in Self::merge_dirty_rects(dirty_rects) {
             for y in top_left.y..bottom_right.y {
                 for x in top_left.x..bottom_right.x {
-                    if let Some(rgb) =
-                        layer.buf[(y - layer.top_left.y) as usize][(x - layer.top_left.x) as usize]
-                    {
-                        self.vram.set_color(Vec2::new(x, y).as_(), rgb)
+                    let color =
+                        self.back_buffer[(y as u32 * self.vram.resolution.x + x as u32) as usize];
+                    self.vram.set_color(Vec2::new(x, y).as_(), color);
+                }
+            }
+        }
+    }
+
+    /// Merges overlapping or edge-adjacent rectangles into their bounding box, repeating until no
+    /// more merges are possible.
+    fn merge_dirty_rects(
+        mut rects: Vec<(Vec2<i32>, Vec2<i32>)>,
+    ) -> Vec<(Vec2<i32>, Vec2<i32>)> {
+        loop {
+            let mut merged_any = false;
+
+            'search: for i in 0..rects.len() {
+                for j in (i + 1)..rects.len() {
+                    if Self::rects_touch(rects[i], rects[j]) {
+                        let (top_left_i, bottom_right_i) = rects[i];
+                        let (top_left_j, bottom_right_j) = rects[j];
+                        let merged = (
+                            Vec2::min(top_left_i, top_left_j),
+                            Vec2::max(bottom_right_i, bottom_right_j),
+                        );
+                        rects.remove(j);
+                        rects.remove(i);
+                        rects.push(merged);
+                        merged_any = true;
+                        break 'search;
                     }
                 }
             }
+
+            if !merged_any {
+                return rects;
+            }
         }
     }
 
+    fn rects_touch(a: (Vec2<i32>, Vec2<i32>), b: (Vec2<i32>, Vec2<i32>)) -> bool {
+        a.0.x <= b.1.x && b.0.x <= a.1.x && a.0.y <= b.1.y && b.0.y <= a.1.y
+    }
+
     fn id_to_layer(&mut self, id: Id) -> Result<&mut Layer, Error> {
         self.collection
             .iter_mut()
             .find(|layer| layer.id == id)
             .ok_or_else(|| Error::NoSuchLayer(id))
     }
+
+    fn id_to_index(&self, id: Id) -> Result<usize, Error> {
+        self.collection
+            .iter()
+            .position(|layer| layer.id == id)
+            .ok_or(Error::NoSuchLayer(id))
+    }
 }
 
 /// Represents a layer.
 #[derive(PartialEq, Eq, Hash, Debug, Default)]
 pub struct Layer {
-    buf: Vec<Vec<Option<RGB8>>>,
+    buf: Vec<Vec<Option<RGBA8>>>,
     top_left: Vec2<i32>,
     len: Vec2<u32>,
     id: Id,
+    blend_mode: BlendMode,
+    scale: Vec2<u32>,
 }
 
 impl Layer {
@@ -224,23 +475,242 @@ impl Layer {
     /// `top_left`, `len`, and `top_left + len`  can be negative, or larger than the resolution of
     /// the screen. In such cases, parts that does not fit in the screen will not be drawn.
     pub fn new(top_left: Vec2<i32>, len: Vec2<u32>) -> Self {
+        Self::new_scaled(top_left, len, Vec2::one())
+    }
+
+    /// Creates an instance of this struct whose backing buffer is smaller than its on-screen size.
+    ///
+    /// The buffer holds `buf_len` pixels, but each one is presented as a `scale.x` by `scale.y`
+    /// block on screen using nearest-neighbor scaling, so the layer's on-screen size ([`len`](Self))
+    /// is `buf_len * scale`. This keeps memory proportional to the logical content while letting a
+    /// small buffer (pixel art, a low-resolution console, ...) be shown larger without allocating
+    /// and filling a full-resolution buffer.
+    ///
+    /// `top_left`, the resulting size, and their sum can be negative, or larger than the resolution
+    /// of the screen. In such cases, parts that does not fit in the screen will not be drawn.
+    pub fn new_scaled(top_left: Vec2<i32>, buf_len: Vec2<u32>, scale: Vec2<u32>) -> Self {
         Self {
-            buf: vec![vec![None; len.x.try_into().unwrap()]; len.y.try_into().unwrap()],
+            buf: vec![vec![None; buf_len.x.try_into().unwrap()]; buf_len.y.try_into().unwrap()],
             top_left,
-            len,
+            len: buf_len * scale,
             id: Id::new(),
+            blend_mode: BlendMode::default(),
+            scale,
         }
     }
 
+    /// Sets the [`BlendMode`] this layer is composited with.
+    #[must_use]
+    pub fn with_blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.blend_mode = blend_mode;
+        self
+    }
+
     fn slide(&mut self, new_top_left: Vec2<i32>) {
         self.top_left = new_top_left;
     }
+
+    /// Returns the dimensions of `self.buf`, in buffer pixels.
+    ///
+    /// This is `self.len` only when `scale` is `(1, 1)`; drawing primitives must clip against
+    /// this, not `self.len`, since `self.len` is the on-screen size after scaling.
+    fn buf_len(&self) -> Vec2<u32> {
+        let height = self.buf.len() as u32;
+        let width = if height == 0 { 0 } else { self.buf[0].len() as u32 };
+        Vec2::new(width, height)
+    }
+
+    /// Blurs `self.buf` in place with a separable Gaussian blur of the given `sigma`.
+    ///
+    /// This runs a horizontal pass followed by a vertical pass, each convolving with a normalized
+    /// Gaussian kernel of radius `ceil(3 * sigma)`. Transparent pixels are treated as alpha `0` and
+    /// contribute no color, so blurred edges fade toward transparent instead of toward black.
+    fn blur(&mut self, sigma: f32) {
+        let kernel = gaussian_kernel(sigma);
+        let horizontal = Self::blur_pass(&self.buf, &kernel, true);
+        self.buf = Self::blur_pass(&horizontal, &kernel, false);
+    }
+
+    fn draw_text(&mut self, origin: Vec2<u32>, text: &str, fg: RGB8, font: &Font) {
+        let buf_len = self.buf_len();
+        let mut cursor = origin;
+
+        for c in text.chars() {
+            if c == '\n' {
+                cursor.x = origin.x;
+                cursor.y += font.glyph_height;
+                continue;
+            }
+
+            if let Some(glyph) = font.glyph(c) {
+                for (row, &bits) in glyph.iter().enumerate() {
+                    for col in 0..font.glyph_width {
+                        if bits & (1 << col) == 0 {
+                            continue;
+                        }
+
+                        let (x, y) = (cursor.x + col, cursor.y + row as u32);
+                        if x < buf_len.x && y < buf_len.y {
+                            self.buf[y as usize][x as usize] =
+                                Some(RGBA8::new(fg.r, fg.g, fg.b, 255));
+                        }
+                    }
+                }
+            }
+
+            cursor.x += font.glyph_width;
+        }
+    }
+
+    /// Sets every pixel of this layer to `color`.
+    pub fn clear(&mut self, color: Option<RGBA8>) {
+        for row in &mut self.buf {
+            for pixel in row {
+                *pixel = color;
+            }
+        }
+    }
+
+    /// Fills the rectangle starting at `top_left` and extending `len` pixels with `color`.
+    ///
+    /// The rectangle is clipped to the bounds of this layer.
+    pub fn fill_rect(&mut self, top_left: Vec2<u32>, len: Vec2<u32>, color: Option<RGBA8>) {
+        let bottom_right = Vec2::min(top_left + len, self.buf_len());
+        for y in top_left.y..bottom_right.y {
+            for x in top_left.x..bottom_right.x {
+                self.buf[y as usize][x as usize] = color;
+            }
+        }
+    }
+
+    /// Draws the outline of the rectangle starting at `top_left` and extending `len` pixels.
+    ///
+    /// The rectangle is clipped to the bounds of this layer.
+    pub fn draw_rect(&mut self, top_left: Vec2<u32>, len: Vec2<u32>, color: Option<RGBA8>) {
+        if len.x == 0 || len.y == 0 {
+            return;
+        }
+
+        let bottom_right = top_left + len - Vec2::one();
+        let top_right = Vec2::new(bottom_right.x, top_left.y);
+        let bottom_left = Vec2::new(top_left.x, bottom_right.y);
+
+        self.draw_line(top_left, top_right, color);
+        self.draw_line(bottom_left, bottom_right, color);
+        self.draw_line(top_left, bottom_left, color);
+        self.draw_line(top_right, bottom_right, color);
+    }
+
+    /// Draws a line from `a` to `b` using Bresenham's line algorithm.
+    ///
+    /// Points outside the bounds of this layer are skipped.
+    pub fn draw_line(&mut self, a: Vec2<u32>, b: Vec2<u32>, color: Option<RGBA8>) {
+        let buf_len = self.buf_len();
+        let (mut x, mut y) = (a.x as i32, a.y as i32);
+        let (x1, y1) = (b.x as i32, b.y as i32);
+
+        let dx = (x1 - x).abs();
+        let dy = -(y1 - y).abs();
+        let step_x = if x < x1 { 1 } else { -1 };
+        let step_y = if y < y1 { 1 } else { -1 };
+        let mut err = dx + dy;
+
+        loop {
+            if x >= 0 && y >= 0 && (x as u32) < buf_len.x && (y as u32) < buf_len.y {
+                self.buf[y as usize][x as usize] = color;
+            }
+
+            if x == x1 && y == y1 {
+                break;
+            }
+
+            let doubled_err = 2 * err;
+            if doubled_err >= dy {
+                err += dy;
+                x += step_x;
+            }
+            if doubled_err <= dx {
+                err += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    fn blur_pass(
+        buf: &[Vec<Option<RGBA8>>],
+        kernel: &[f32],
+        horizontal: bool,
+    ) -> Vec<Vec<Option<RGBA8>>> {
+        let height = buf.len();
+        let width = if height == 0 { 0 } else { buf[0].len() };
+        let radius = (kernel.len() / 2) as i32;
+
+        let mut out = vec![vec![None; width]; height];
+        for (y, row) in out.iter_mut().enumerate() {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let mut sum_r = 0.0_f32;
+                let mut sum_g = 0.0_f32;
+                let mut sum_b = 0.0_f32;
+                let mut sum_a = 0.0_f32;
+
+                for (i, &weight) in kernel.iter().enumerate() {
+                    let offset = i as i32 - radius;
+                    let (sx, sy) = if horizontal {
+                        (clamp_to_edge(x as i32 + offset, width), y)
+                    } else {
+                        (x, clamp_to_edge(y as i32 + offset, height))
+                    };
+
+                    if let Some(src) = buf[sy][sx] {
+                        let a = f32::from(src.a) / 255.0;
+                        sum_r += weight * a * f32::from(src.r);
+                        sum_g += weight * a * f32::from(src.g);
+                        sum_b += weight * a * f32::from(src.b);
+                        sum_a += weight * a;
+                    }
+                }
+
+                *pixel = if sum_a > 0.0 {
+                    Some(RGBA8::new(
+                        (sum_r / sum_a) as u8,
+                        (sum_g / sum_a) as u8,
+                        (sum_b / sum_a) as u8,
+                        (sum_a * 255.0) as u8,
+                    ))
+                } else {
+                    None
+                };
+            }
+        }
+
+        out
+    }
+
+    /// Returns the color of the pixel at the given VRAM coordinate, or `None` if the coordinate
+    /// is outside of the layer, or the pixel is transparent.
+    fn pixel_at(&self, vram_coord: Vec2<i32>) -> Option<RGBA8> {
+        let bottom_right = self.top_left + self.len.as_();
+        if vram_coord.x < self.top_left.x
+            || vram_coord.y < self.top_left.y
+            || vram_coord.x >= bottom_right.x
+            || vram_coord.y >= bottom_right.y
+        {
+            return None;
+        }
+
+        let local = vram_coord - self.top_left;
+        let buf_coord = Vec2::new(
+            local.x as u32 / self.scale.x,
+            local.y as u32 / self.scale.y,
+        );
+        self.buf[buf_coord.y as usize][buf_coord.x as usize]
+    }
 }
 
 /// Layer can index into each pixels.
 impl Index<usize> for Layer {
     /// `None` represents the pixel is transparent.
-    type Output = [Option<RGB8>];
+    type Output = [Option<RGBA8>];
 
     fn index(&self, index: usize) -> &Self::Output {
         &self.buf[index]
@@ -253,6 +723,236 @@ impl IndexMut<usize> for Layer {
     }
 }
 
+/// How a layer's colors are combined with the layers behind it.
+///
+/// The blend mode is applied to produce the source color, which is then alpha-composited onto
+/// the destination (the pixel accumulated so far from layers behind this one) using this layer's
+/// alpha channel.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub enum BlendMode {
+    /// The source color is used as-is.
+    #[default]
+    Normal,
+    /// Each channel is the product of the source and destination channels.
+    Multiply,
+    /// Each channel is the inverse of the product of the inverted source and destination channels.
+    Screen,
+    /// Each channel is the saturating sum of the source and destination channels.
+    Add,
+}
+
+impl BlendMode {
+    fn blend_channel(self, src: u8, dst: u8) -> u8 {
+        let (src16, dst16) = (u16::from(src), u16::from(dst));
+        match self {
+            Self::Normal => src,
+            Self::Multiply => (src16 * dst16 / 255) as u8,
+            Self::Screen => (255 - (255 - src16) * (255 - dst16) / 255) as u8,
+            Self::Add => (src16 + dst16).min(255) as u8,
+        }
+    }
+
+    /// Blends `src` with `dst`, then alpha-composites the result onto `dst` using `src`'s alpha.
+    fn composite(self, src: RGBA8, dst: RGB8) -> RGB8 {
+        let blended = RGB8::new(
+            self.blend_channel(src.r, dst.r),
+            self.blend_channel(src.g, dst.g),
+            self.blend_channel(src.b, dst.b),
+        );
+
+        let a = u16::from(src.a);
+        let mix = |src: u8, dst: u8| -> u8 {
+            ((u16::from(src) * a + u16::from(dst) * (255 - a)) / 255) as u8
+        };
+        RGB8::new(mix(blended.r, dst.r), mix(blended.g, dst.g), mix(blended.b, dst.b))
+    }
+}
+
+/// Builds a normalized 1-D Gaussian kernel of radius `ceil(3 * sigma)`.
+fn gaussian_kernel(sigma: f32) -> Vec<f32> {
+    let sigma = if sigma > 0.0 { sigma } else { f32::EPSILON };
+    let radius = libm::ceilf(3.0 * sigma) as i32;
+
+    let mut kernel: Vec<f32> = (-radius..=radius)
+        .map(|x| libm::expf(-(x * x) as f32 / (2.0 * sigma * sigma)))
+        .collect();
+
+    let sum: f32 = kernel.iter().sum();
+    for weight in &mut kernel {
+        *weight /= sum;
+    }
+
+    kernel
+}
+
+/// Clamps `v` to `0..len`, used to sample at the edge of a buffer instead of going out of bounds.
+fn clamp_to_edge(v: i32, len: usize) -> usize {
+    v.max(0).min(len as i32 - 1) as usize
+}
+
+/// A fixed-cell bitmap font, used by [`Controller::draw_text`].
+///
+/// Glyphs are indexed by ASCII codepoint. Each glyph is `glyph_height` bytes, one byte per row,
+/// with bit `x` (`1 << x`) set when column `x` of that row should be drawn.
+#[derive(Copy, Clone, Debug)]
+pub struct Font<'a> {
+    glyph_width: u32,
+    glyph_height: u32,
+    rows: &'a [u8],
+}
+impl<'a> Font<'a> {
+    /// Creates a font from `rows`, the glyphs for codepoint `0`, `1`, `2`, ... concatenated in
+    /// order, `glyph_height` bytes each.
+    pub const fn new(glyph_width: u32, glyph_height: u32, rows: &'a [u8]) -> Self {
+        Self {
+            glyph_width,
+            glyph_height,
+            rows,
+        }
+    }
+
+    fn glyph(&self, c: char) -> Option<&[u8]> {
+        let height = self.glyph_height as usize;
+        let index = (c as usize).checked_mul(height)?;
+        self.rows.get(index..index + height)
+    }
+}
+
+/// The default 8x8 bitmap font, covering the printable ASCII range.
+pub static DEFAULT_FONT: Font<'static> = Font::new(8, 8, &DEFAULT_FONT_ROWS);
+
+#[rustfmt::skip]
+static DEFAULT_FONT_ROWS: [u8; 128 * 8] = [
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // U+0000..U+001F: unprintable control codes
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // U+0020 (space)
+    0x18, 0x3C, 0x3C, 0x18, 0x18, 0x00, 0x18, 0x00, // U+0021 (!)
+    0x36, 0x36, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // U+0022 (")
+    0x36, 0x36, 0x7F, 0x36, 0x7F, 0x36, 0x36, 0x00, // U+0023 (#)
+    0x0C, 0x3E, 0x03, 0x1E, 0x30, 0x1F, 0x0C, 0x00, // U+0024 ($)
+    0x00, 0x63, 0x33, 0x18, 0x0C, 0x66, 0x63, 0x00, // U+0025 (%)
+    0x1C, 0x36, 0x1C, 0x6E, 0x3B, 0x33, 0x6E, 0x00, // U+0026 (&)
+    0x06, 0x06, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, // U+0027 (')
+    0x18, 0x0C, 0x06, 0x06, 0x06, 0x0C, 0x18, 0x00, // U+0028 (()
+    0x06, 0x0C, 0x18, 0x18, 0x18, 0x0C, 0x06, 0x00, // U+0029 ())
+    0x00, 0x66, 0x3C, 0xFF, 0x3C, 0x66, 0x00, 0x00, // U+002A (*)
+    0x00, 0x0C, 0x0C, 0x3F, 0x0C, 0x0C, 0x00, 0x00, // U+002B (+)
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C, 0x06, // U+002C (,)
+    0x00, 0x00, 0x00, 0x3F, 0x00, 0x00, 0x00, 0x00, // U+002D (-)
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C, 0x00, // U+002E (.)
+    0x60, 0x30, 0x18, 0x0C, 0x06, 0x03, 0x01, 0x00, // U+002F (/)
+    0x3E, 0x63, 0x73, 0x7B, 0x6F, 0x67, 0x3E, 0x00, // U+0030 (0)
+    0x0C, 0x0E, 0x0C, 0x0C, 0x0C, 0x0C, 0x3F, 0x00, // U+0031 (1)
+    0x1E, 0x33, 0x30, 0x1C, 0x06, 0x33, 0x3F, 0x00, // U+0032 (2)
+    0x1E, 0x33, 0x30, 0x1C, 0x30, 0x33, 0x1E, 0x00, // U+0033 (3)
+    0x38, 0x3C, 0x36, 0x33, 0x7F, 0x30, 0x78, 0x00, // U+0034 (4)
+    0x3F, 0x03, 0x1F, 0x30, 0x30, 0x33, 0x1E, 0x00, // U+0035 (5)
+    0x1C, 0x06, 0x03, 0x1F, 0x33, 0x33, 0x1E, 0x00, // U+0036 (6)
+    0x3F, 0x33, 0x30, 0x18, 0x0C, 0x0C, 0x0C, 0x00, // U+0037 (7)
+    0x1E, 0x33, 0x33, 0x1E, 0x33, 0x33, 0x1E, 0x00, // U+0038 (8)
+    0x1E, 0x33, 0x33, 0x3E, 0x30, 0x18, 0x0E, 0x00, // U+0039 (9)
+    0x00, 0x0C, 0x0C, 0x00, 0x00, 0x0C, 0x0C, 0x00, // U+003A (:)
+    0x00, 0x0C, 0x0C, 0x00, 0x00, 0x0C, 0x0C, 0x06, // U+003B (;)
+    0x18, 0x0C, 0x06, 0x03, 0x06, 0x0C, 0x18, 0x00, // U+003C (<)
+    0x00, 0x00, 0x3F, 0x00, 0x00, 0x3F, 0x00, 0x00, // U+003D (=)
+    0x06, 0x0C, 0x18, 0x30, 0x18, 0x0C, 0x06, 0x00, // U+003E (>)
+    0x1E, 0x33, 0x30, 0x18, 0x0C, 0x00, 0x0C, 0x00, // U+003F (?)
+    0x3E, 0x63, 0x7B, 0x7B, 0x7B, 0x03, 0x1E, 0x00, // U+0040 (@)
+    0x0C, 0x1E, 0x33, 0x33, 0x3F, 0x33, 0x33, 0x00, // U+0041 (A)
+    0x3F, 0x66, 0x66, 0x3E, 0x66, 0x66, 0x3F, 0x00, // U+0042 (B)
+    0x3C, 0x66, 0x03, 0x03, 0x03, 0x66, 0x3C, 0x00, // U+0043 (C)
+    0x1F, 0x36, 0x66, 0x66, 0x66, 0x36, 0x1F, 0x00, // U+0044 (D)
+    0x7F, 0x46, 0x16, 0x1E, 0x16, 0x46, 0x7F, 0x00, // U+0045 (E)
+    0x7F, 0x46, 0x16, 0x1E, 0x16, 0x06, 0x0F, 0x00, // U+0046 (F)
+    0x3C, 0x66, 0x03, 0x03, 0x73, 0x66, 0x7C, 0x00, // U+0047 (G)
+    0x33, 0x33, 0x33, 0x3F, 0x33, 0x33, 0x33, 0x00, // U+0048 (H)
+    0x1E, 0x0C, 0x0C, 0x0C, 0x0C, 0x0C, 0x1E, 0x00, // U+0049 (I)
+    0x78, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1E, 0x00, // U+004A (J)
+    0x67, 0x66, 0x36, 0x1E, 0x36, 0x66, 0x67, 0x00, // U+004B (K)
+    0x0F, 0x06, 0x06, 0x06, 0x46, 0x66, 0x7F, 0x00, // U+004C (L)
+    0x63, 0x77, 0x7F, 0x7F, 0x6B, 0x63, 0x63, 0x00, // U+004D (M)
+    0x63, 0x67, 0x6F, 0x7B, 0x73, 0x63, 0x63, 0x00, // U+004E (N)
+    0x1C, 0x36, 0x63, 0x63, 0x63, 0x36, 0x1C, 0x00, // U+004F (O)
+    0x3F, 0x66, 0x66, 0x3E, 0x06, 0x06, 0x0F, 0x00, // U+0050 (P)
+    0x1E, 0x33, 0x33, 0x33, 0x3B, 0x1E, 0x38, 0x00, // U+0051 (Q)
+    0x3F, 0x66, 0x66, 0x3E, 0x36, 0x66, 0x67, 0x00, // U+0052 (R)
+    0x1E, 0x33, 0x07, 0x0E, 0x38, 0x33, 0x1E, 0x00, // U+0053 (S)
+    0x3F, 0x2D, 0x0C, 0x0C, 0x0C, 0x0C, 0x1E, 0x00, // U+0054 (T)
+    0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x3F, 0x00, // U+0055 (U)
+    0x33, 0x33, 0x33, 0x33, 0x33, 0x1E, 0x0C, 0x00, // U+0056 (V)
+    0x63, 0x63, 0x63, 0x6B, 0x7F, 0x77, 0x63, 0x00, // U+0057 (W)
+    0x63, 0x63, 0x36, 0x1C, 0x1C, 0x36, 0x63, 0x00, // U+0058 (X)
+    0x33, 0x33, 0x33, 0x1E, 0x0C, 0x0C, 0x1E, 0x00, // U+0059 (Y)
+    0x7F, 0x63, 0x31, 0x18, 0x4C, 0x66, 0x7F, 0x00, // U+005A (Z)
+    0x1E, 0x06, 0x06, 0x06, 0x06, 0x06, 0x1E, 0x00, // U+005B ([)
+    0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x40, 0x00, // U+005C (\)
+    0x1E, 0x18, 0x18, 0x18, 0x18, 0x18, 0x1E, 0x00, // U+005D (])
+    0x08, 0x1C, 0x36, 0x63, 0x00, 0x00, 0x00, 0x00, // U+005E (^)
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF, // U+005F (_)
+    0x0C, 0x0C, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00, // U+0060 (`)
+    0x00, 0x00, 0x1E, 0x30, 0x3E, 0x33, 0x6E, 0x00, // U+0061 (a)
+    0x07, 0x06, 0x06, 0x3E, 0x66, 0x66, 0x3B, 0x00, // U+0062 (b)
+    0x00, 0x00, 0x1E, 0x33, 0x03, 0x33, 0x1E, 0x00, // U+0063 (c)
+    0x38, 0x30, 0x30, 0x3E, 0x33, 0x33, 0x6E, 0x00, // U+0064 (d)
+    0x00, 0x00, 0x1E, 0x33, 0x3F, 0x03, 0x1E, 0x00, // U+0065 (e)
+    0x1C, 0x36, 0x06, 0x0F, 0x06, 0x06, 0x0F, 0x00, // U+0066 (f)
+    0x00, 0x00, 0x6E, 0x33, 0x33, 0x3E, 0x30, 0x1F, // U+0067 (g)
+    0x07, 0x06, 0x36, 0x6E, 0x66, 0x66, 0x67, 0x00, // U+0068 (h)
+    0x0C, 0x00, 0x0E, 0x0C, 0x0C, 0x0C, 0x1E, 0x00, // U+0069 (i)
+    0x30, 0x00, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1E, // U+006A (j)
+    0x07, 0x06, 0x66, 0x36, 0x1E, 0x36, 0x67, 0x00, // U+006B (k)
+    0x0E, 0x0C, 0x0C, 0x0C, 0x0C, 0x0C, 0x1E, 0x00, // U+006C (l)
+    0x00, 0x00, 0x33, 0x7F, 0x7F, 0x6B, 0x63, 0x00, // U+006D (m)
+    0x00, 0x00, 0x1F, 0x33, 0x33, 0x33, 0x33, 0x00, // U+006E (n)
+    0x00, 0x00, 0x1E, 0x33, 0x33, 0x33, 0x1E, 0x00, // U+006F (o)
+    0x00, 0x00, 0x3B, 0x66, 0x66, 0x3E, 0x06, 0x0F, // U+0070 (p)
+    0x00, 0x00, 0x6E, 0x33, 0x33, 0x3E, 0x30, 0x78, // U+0071 (q)
+    0x00, 0x00, 0x3B, 0x6E, 0x66, 0x06, 0x0F, 0x00, // U+0072 (r)
+    0x00, 0x00, 0x3E, 0x03, 0x1E, 0x30, 0x1F, 0x00, // U+0073 (s)
+    0x08, 0x0C, 0x3E, 0x0C, 0x0C, 0x2C, 0x18, 0x00, // U+0074 (t)
+    0x00, 0x00, 0x33, 0x33, 0x33, 0x33, 0x6E, 0x00, // U+0075 (u)
+    0x00, 0x00, 0x33, 0x33, 0x33, 0x1E, 0x0C, 0x00, // U+0076 (v)
+    0x00, 0x00, 0x63, 0x6B, 0x7F, 0x7F, 0x36, 0x00, // U+0077 (w)
+    0x00, 0x00, 0x63, 0x36, 0x1C, 0x36, 0x63, 0x00, // U+0078 (x)
+    0x00, 0x00, 0x33, 0x33, 0x33, 0x3E, 0x30, 0x1F, // U+0079 (y)
+    0x00, 0x00, 0x3F, 0x19, 0x0C, 0x26, 0x3F, 0x00, // U+007A (z)
+    0x38, 0x0C, 0x0C, 0x07, 0x0C, 0x0C, 0x38, 0x00, // U+007B ({)
+    0x18, 0x18, 0x18, 0x00, 0x18, 0x18, 0x18, 0x00, // U+007C (|)
+    0x07, 0x0C, 0x0C, 0x38, 0x0C, 0x0C, 0x07, 0x00, // U+007D (})
+    0x6E, 0x3B, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // U+007E (~)
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // U+007F (del)
+];
+
 /// An almost unique id to distinguish each layer.
 ///
 /// You have to save this id to edit, and slide a layer.
@@ -275,18 +975,84 @@ pub enum Error {
     NoSuchLayer(Id),
 }
 
+/// The pixel layout of a VRAM framebuffer.
+///
+/// Framebuffers on real hardware come in many layouts, so [`Controller::new`] takes one of these
+/// instead of assuming a fixed 24/32-bit BGR buffer. Each variant knows how many bytes one pixel
+/// occupies and how to pack an [`RGB8`] into those bytes.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub enum PixelFormat {
+    /// 24 bits per pixel, stored as blue, green, then red.
+    #[default]
+    Bgr888,
+    /// 32 bits per pixel, stored as blue, green, red, then an unused byte.
+    Bgra8888,
+    /// 24 bits per pixel, stored as red, green, then blue.
+    Rgb888,
+    /// 32 bits per pixel, stored as red, green, blue, then an unused byte.
+    Rgba8888,
+    /// 16 bits per pixel, packed little-endian as 5 bits red, 6 bits green, and 5 bits blue.
+    Rgb565,
+    /// 8 bits per pixel, storing the luma of the color.
+    Mono8,
+}
+impl PixelFormat {
+    /// Returns how many bytes one pixel occupies in this format.
+    fn bytes_per_pixel(self) -> usize {
+        match self {
+            Self::Bgr888 | Self::Rgb888 => 3,
+            Self::Bgra8888 | Self::Rgba8888 => 4,
+            Self::Rgb565 => 2,
+            Self::Mono8 => 1,
+        }
+    }
+
+    /// Packs `rgb` and writes it to `ptr` according to this format.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for writes of [`Self::bytes_per_pixel`] bytes.
+    unsafe fn write(self, ptr: *mut u8, rgb: RGB8) {
+        // Using `offset` causes UB. See the official doc of `offset` method.
+        match self {
+            Self::Bgr888 | Self::Bgra8888 => {
+                ptr::write(ptr, rgb.b);
+                ptr::write(ptr.add(size_of::<u8>()), rgb.g);
+                ptr::write(ptr.add(size_of::<u8>() * 2), rgb.r);
+            }
+            Self::Rgb888 | Self::Rgba8888 => {
+                ptr::write(ptr, rgb.r);
+                ptr::write(ptr.add(size_of::<u8>()), rgb.g);
+                ptr::write(ptr.add(size_of::<u8>() * 2), rgb.b);
+            }
+            Self::Rgb565 => {
+                let packed = (u16::from(rgb.r >> 3) << 11)
+                    | (u16::from(rgb.g >> 2) << 5)
+                    | u16::from(rgb.b >> 3);
+                ptr::write(ptr, packed.to_le_bytes()[0]);
+                ptr::write(ptr.add(size_of::<u8>()), packed.to_le_bytes()[1]);
+            }
+            Self::Mono8 => {
+                let luma = (u16::from(rgb.r) * 77 + u16::from(rgb.g) * 150 + u16::from(rgb.b) * 29)
+                    >> 8;
+                ptr::write(ptr, luma as u8);
+            }
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 struct Vram {
     resolution: Vec2<u32>,
-    bpp: u32,
+    format: PixelFormat,
     base_addr: usize,
 }
 
 impl Vram {
-    fn new(resolution: Vec2<u32>, bpp: u32, base_addr: usize) -> Self {
+    fn new(resolution: Vec2<u32>, format: PixelFormat, base_addr: usize) -> Self {
         Self {
             resolution,
-            bpp,
+            format,
             base_addr,
         }
     }
@@ -297,15 +1063,499 @@ impl Vram {
             coord
         );
 
-        let offset_from_base = ((coord.y * self.resolution.x + coord.x) * self.bpp / 8) as isize;
-        let ptr = (self.base_addr as isize + offset_from_base) as usize;
+        let offset_from_base = (coord.y * self.resolution.x + coord.x) as usize
+            * self.format.bytes_per_pixel();
+        let ptr = (self.base_addr + offset_from_base) as *mut u8;
 
-        // Using `offset` causes UB. See the official doc of `offset` method.
-        // TODO: Support for other orders of RGB.
         unsafe {
-            ptr::write(ptr as _, rgb.b);
-            ptr::write((ptr + size_of::<u8>()) as _, rgb.g);
-            ptr::write((ptr + size_of::<u8>() * 2) as _, rgb.r);
+            self.format.write(ptr, rgb);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blend_mode_normal_ignores_destination() {
+        assert_eq!(BlendMode::Normal.blend_channel(200, 50), 200);
+    }
+
+    #[test]
+    fn blend_mode_multiply_darkens() {
+        assert_eq!(BlendMode::Multiply.blend_channel(255, 128), 128);
+        assert_eq!(BlendMode::Multiply.blend_channel(0, 255), 0);
+        assert_eq!(BlendMode::Multiply.blend_channel(128, 128), 64);
+    }
+
+    #[test]
+    fn blend_mode_screen_lightens() {
+        assert_eq!(BlendMode::Screen.blend_channel(255, 128), 255);
+        assert_eq!(BlendMode::Screen.blend_channel(0, 0), 0);
+        assert_eq!(BlendMode::Screen.blend_channel(128, 128), 192);
+    }
+
+    #[test]
+    fn blend_mode_add_saturates() {
+        assert_eq!(BlendMode::Add.blend_channel(200, 100), 255);
+        assert_eq!(BlendMode::Add.blend_channel(10, 20), 30);
+    }
+
+    #[test]
+    fn composite_respects_source_alpha() {
+        let dst = RGB8::new(0, 0, 0);
+        let opaque_src = RGBA8::new(255, 0, 0, 255);
+        assert_eq!(BlendMode::Normal.composite(opaque_src, dst), RGB8::new(255, 0, 0));
+
+        let half_src = RGBA8::new(255, 0, 0, 128);
+        let blended = BlendMode::Normal.composite(half_src, dst);
+        assert!(blended.r > 120 && blended.r < 135);
+        assert_eq!(blended.g, 0);
+        assert_eq!(blended.b, 0);
+
+        let transparent_src = RGBA8::new(255, 0, 0, 0);
+        assert_eq!(BlendMode::Normal.composite(transparent_src, dst), dst);
+    }
+
+    #[test]
+    fn blur_with_zero_sigma_leaves_a_solid_layer_unchanged() {
+        let mut layer = Layer::new(Vec2::new(0, 0), Vec2::new(3, 3));
+        for y in 0..3 {
+            for x in 0..3 {
+                layer[y][x] = Some(RGBA8::new(100, 150, 200, 255));
+            }
+        }
+
+        layer.blur(0.0);
+
+        for y in 0..3 {
+            for x in 0..3 {
+                assert_eq!(layer[y][x], Some(RGBA8::new(100, 150, 200, 255)));
+            }
+        }
+    }
+
+    #[test]
+    fn blur_fades_a_lone_opaque_pixel_toward_transparent() {
+        let mut layer = Layer::new(Vec2::new(0, 0), Vec2::new(11, 11));
+        layer[5][5] = Some(RGBA8::new(255, 255, 255, 255));
+
+        layer.blur(1.0);
+
+        let center = layer[5][5].unwrap();
+        assert!(center.a < 255, "blurred center pixel should lose some alpha");
+        assert!(
+            layer[0][0].is_none(),
+            "a pixel far outside the kernel's radius should stay transparent"
+        );
+    }
+
+    #[test]
+    fn font_glyph_looks_up_by_ascii_codepoint() {
+        assert_eq!(DEFAULT_FONT.glyph(' '), DEFAULT_FONT_ROWS.get(0x20 * 8..0x20 * 8 + 8));
+        assert_eq!(DEFAULT_FONT.glyph('A'), DEFAULT_FONT_ROWS.get(0x41 * 8..0x41 * 8 + 8));
+        assert_ne!(DEFAULT_FONT.glyph('A'), DEFAULT_FONT.glyph('B'));
+    }
+
+    #[test]
+    fn font_glyph_is_none_past_the_table() {
+        let font = Font::new(8, 8, &[0u8; 16]);
+        assert!(font.glyph('\u{1}').is_some());
+        assert!(font.glyph('\u{2}').is_none());
+    }
+
+    #[test]
+    fn draw_text_sets_only_the_glyphs_pixels() {
+        let mut layer = Layer::new(Vec2::new(0, 0), Vec2::new(8, 8));
+        layer.draw_text(Vec2::new(0, 0), "A", RGB8::new(1, 2, 3), &DEFAULT_FONT);
+
+        let glyph = DEFAULT_FONT.glyph('A').unwrap();
+        for (row, &bits) in glyph.iter().enumerate() {
+            for col in 0..8u32 {
+                let expected = if bits & (1 << col) != 0 {
+                    Some(RGBA8::new(1, 2, 3, 255))
+                } else {
+                    None
+                };
+                assert_eq!(layer[row][col as usize], expected);
+            }
+        }
+    }
+
+    #[test]
+    fn draw_text_newline_moves_to_next_glyph_row() {
+        let mut layer = Layer::new(Vec2::new(0, 0), Vec2::new(16, 16));
+        layer.draw_text(Vec2::new(0, 0), "A\nB", RGB8::new(9, 9, 9), &DEFAULT_FONT);
+
+        // Second glyph starts back under the origin column, one glyph row down.
+        let glyph_b = DEFAULT_FONT.glyph('B').unwrap();
+        for (row, &bits) in glyph_b.iter().enumerate() {
+            for col in 0..8u32 {
+                if bits & (1 << col) != 0 {
+                    assert_eq!(layer[8 + row][col as usize], Some(RGBA8::new(9, 9, 9, 255)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fill_rect_clips_to_layer_bounds() {
+        let mut layer = Layer::new(Vec2::new(0, 0), Vec2::new(4, 4));
+        layer.fill_rect(Vec2::new(2, 2), Vec2::new(10, 10), Some(RGBA8::new(1, 1, 1, 255)));
+
+        for y in 0..4 {
+            for x in 0..4 {
+                let expected = if x >= 2 && y >= 2 { Some(RGBA8::new(1, 1, 1, 255)) } else { None };
+                assert_eq!(layer[y][x], expected);
+            }
+        }
+    }
+
+    #[test]
+    fn draw_line_is_a_straight_diagonal() {
+        let mut layer = Layer::new(Vec2::new(0, 0), Vec2::new(4, 4));
+        layer.draw_line(Vec2::new(0, 0), Vec2::new(3, 3), Some(RGBA8::new(2, 2, 2, 255)));
+
+        for i in 0..4 {
+            assert_eq!(layer[i][i], Some(RGBA8::new(2, 2, 2, 255)));
+        }
+        assert_eq!(layer[0][1], None);
+        assert_eq!(layer[1][0], None);
+    }
+
+    #[test]
+    fn draw_rect_draws_only_the_outline() {
+        let mut layer = Layer::new(Vec2::new(0, 0), Vec2::new(4, 4));
+        layer.draw_rect(Vec2::new(0, 0), Vec2::new(4, 4), Some(RGBA8::new(3, 3, 3, 255)));
+
+        assert_eq!(layer[0][0], Some(RGBA8::new(3, 3, 3, 255)));
+        assert_eq!(layer[0][3], Some(RGBA8::new(3, 3, 3, 255)));
+        assert_eq!(layer[3][0], Some(RGBA8::new(3, 3, 3, 255)));
+        assert_eq!(layer[3][3], Some(RGBA8::new(3, 3, 3, 255)));
+        assert_eq!(layer[1][1], None, "interior of the rectangle should be untouched");
+    }
+
+    #[test]
+    fn blit_copies_a_sub_rectangle_and_skips_transparent_source_pixels() {
+        let mut vram = [0u8; 10 * 10 * 4];
+        let mut controller = unsafe {
+            Controller::new(Vec2::new(10, 10), PixelFormat::Bgra8888, vram.as_mut_ptr() as usize)
+        };
+
+        let mut src = Layer::new(Vec2::new(0, 0), Vec2::new(2, 2));
+        src[0][0] = Some(RGBA8::new(9, 9, 9, 255));
+        src[0][1] = None;
+        let src_id = controller.add_layer(src);
+
+        let dst = Layer::new(Vec2::new(0, 0), Vec2::new(4, 4));
+        let dst_id = controller.add_layer(dst);
+        controller.edit_layer(dst_id, |layer| {
+            layer[1][1] = Some(RGBA8::new(5, 5, 5, 255));
+        }).unwrap();
+
+        controller
+            .blit(dst_id, Vec2::new(1, 0), src_id, Vec2::new(0, 0), Vec2::new(2, 2))
+            .unwrap();
+
+        let dst_layer = controller.id_to_layer(dst_id).unwrap();
+        assert_eq!(dst_layer[0][1], Some(RGBA8::new(9, 9, 9, 255)));
+        assert_eq!(dst_layer[1][1], Some(RGBA8::new(5, 5, 5, 255)), "untouched by a None source pixel");
+    }
+
+    #[test]
+    fn blit_clips_to_the_source_layers_buffer() {
+        let mut vram = [0u8; 10 * 10 * 4];
+        let mut controller = unsafe {
+            Controller::new(Vec2::new(10, 10), PixelFormat::Bgra8888, vram.as_mut_ptr() as usize)
+        };
+
+        let mut src = Layer::new(Vec2::new(0, 0), Vec2::new(2, 2));
+        src[1][1] = Some(RGBA8::new(7, 7, 7, 255));
+        let src_id = controller.add_layer(src);
+        let dst_id = controller.add_layer(Layer::new(Vec2::new(0, 0), Vec2::new(4, 4)));
+
+        // Asking for an 8x8 sub-rectangle of a 2x2 source must not panic or read out of bounds.
+        controller
+            .blit(dst_id, Vec2::new(0, 0), src_id, Vec2::new(0, 0), Vec2::new(8, 8))
+            .unwrap();
+
+        let dst_layer = controller.id_to_layer(dst_id).unwrap();
+        assert_eq!(dst_layer[1][1], Some(RGBA8::new(7, 7, 7, 255)));
+    }
+
+    #[test]
+    fn new_scaled_reports_the_scaled_on_screen_size() {
+        let layer = Layer::new_scaled(Vec2::new(0, 0), Vec2::new(4, 4), Vec2::new(3, 2));
+        assert_eq!(layer.len, Vec2::new(12, 8));
+        assert_eq!(layer.buf_len(), Vec2::new(4, 4));
+    }
+
+    #[test]
+    fn pixel_at_maps_each_on_screen_block_back_to_one_buffer_pixel() {
+        let mut layer = Layer::new_scaled(Vec2::new(0, 0), Vec2::new(2, 2), Vec2::new(3, 3));
+        layer[0][0] = Some(RGBA8::new(1, 0, 0, 255));
+        layer[0][1] = Some(RGBA8::new(0, 1, 0, 255));
+        layer[1][0] = Some(RGBA8::new(0, 0, 1, 255));
+        layer[1][1] = Some(RGBA8::new(1, 1, 1, 255));
+
+        for y in 0..6 {
+            for x in 0..6 {
+                let (buf_x, buf_y) = (x / 3, y / 3);
+                assert_eq!(layer.pixel_at(Vec2::new(x, y)), layer.buf[buf_y as usize][buf_x as usize]);
+            }
         }
     }
+
+    #[test]
+    fn drawing_primitives_clip_to_the_buffer_not_the_scaled_on_screen_size() {
+        // Regression test: a scaled layer's `len` (on-screen size) is larger than its `buf`, and
+        // every drawing primitive must clip against the buffer, not against `len`, or it panics
+        // with an out-of-bounds index as soon as a coordinate is within `len` but outside `buf`.
+        let mut layer = Layer::new_scaled(Vec2::new(0, 0), Vec2::new(4, 4), Vec2::new(3, 3));
+
+        layer.fill_rect(Vec2::new(0, 0), Vec2::new(20, 20), Some(RGBA8::new(1, 1, 1, 255)));
+        layer.draw_rect(Vec2::new(0, 0), Vec2::new(20, 20), Some(RGBA8::new(1, 1, 1, 255)));
+        layer.draw_line(Vec2::new(0, 0), Vec2::new(10, 10), Some(RGBA8::new(1, 1, 1, 255)));
+        layer.draw_text(Vec2::new(0, 0), "A", RGB8::new(1, 1, 1), &DEFAULT_FONT);
+
+        let mut vram = [0u8; 40 * 40 * 4];
+        let mut controller = unsafe {
+            Controller::new(Vec2::new(40, 40), PixelFormat::Bgra8888, vram.as_mut_ptr() as usize)
+        };
+        let other = controller.add_layer(Layer::new(Vec2::new(0, 0), Vec2::new(4, 4)));
+        let scaled = controller.add_layer(layer);
+        controller
+            .blit(scaled, Vec2::new(0, 0), other, Vec2::new(0, 0), Vec2::new(4, 4))
+            .unwrap();
+    }
+
+    #[test]
+    fn pixel_format_rgb565_packs_5_6_5_bits_little_endian() {
+        let mut buf = [0u8; 2];
+        unsafe { PixelFormat::Rgb565.write(buf.as_mut_ptr(), RGB8::new(8, 4, 8)) };
+        assert_eq!(buf, [0x21, 0x08]);
+
+        let mut white = [0u8; 2];
+        unsafe { PixelFormat::Rgb565.write(white.as_mut_ptr(), RGB8::new(255, 255, 255)) };
+        assert_eq!(white, [0xFF, 0xFF]);
+
+        let mut black = [0u8; 2];
+        unsafe { PixelFormat::Rgb565.write(black.as_mut_ptr(), RGB8::new(0, 0, 0)) };
+        assert_eq!(black, [0x00, 0x00]);
+    }
+
+    #[test]
+    fn pixel_format_mono8_writes_the_rec601_luma() {
+        let mut buf = [0u8; 1];
+        unsafe { PixelFormat::Mono8.write(buf.as_mut_ptr(), RGB8::new(0, 128, 64)) };
+        assert_eq!(buf, [82]);
+
+        let mut red = [0u8; 1];
+        unsafe { PixelFormat::Mono8.write(red.as_mut_ptr(), RGB8::new(255, 0, 0)) };
+        assert_eq!(red, [76]);
+
+        let mut white = [0u8; 1];
+        unsafe { PixelFormat::Mono8.write(white.as_mut_ptr(), RGB8::new(255, 255, 255)) };
+        assert_eq!(white, [255]);
+    }
+
+    #[test]
+    fn pixel_format_bgr_and_rgb_variants_write_in_their_named_byte_order() {
+        let color = RGB8::new(1, 2, 3);
+
+        let mut bgr = [0u8; 3];
+        unsafe { PixelFormat::Bgr888.write(bgr.as_mut_ptr(), color) };
+        assert_eq!(bgr, [3, 2, 1]);
+
+        let mut rgb = [0u8; 3];
+        unsafe { PixelFormat::Rgb888.write(rgb.as_mut_ptr(), color) };
+        assert_eq!(rgb, [1, 2, 3]);
+
+        let mut bgra = [0u8; 4];
+        unsafe { PixelFormat::Bgra8888.write(bgra.as_mut_ptr(), color) };
+        assert_eq!(&bgra[..3], &[3, 2, 1]);
+
+        let mut rgba = [0u8; 4];
+        unsafe { PixelFormat::Rgba8888.write(rgba.as_mut_ptr(), color) };
+        assert_eq!(&rgba[..3], &[1, 2, 3]);
+    }
+
+    #[test]
+    fn rects_touch_detects_overlap_adjacency_and_disjointness() {
+        let a = (Vec2::new(0, 0), Vec2::new(10, 10));
+
+        // Overlapping.
+        assert!(Controller::rects_touch(a, (Vec2::new(5, 5), Vec2::new(15, 15))));
+        // Edge-adjacent (shares the boundary at x == 10).
+        assert!(Controller::rects_touch(a, (Vec2::new(10, 0), Vec2::new(20, 10))));
+        // Disjoint, with a one-pixel gap.
+        assert!(!Controller::rects_touch(a, (Vec2::new(11, 0), Vec2::new(20, 10))));
+    }
+
+    #[test]
+    fn merge_dirty_rects_collapses_overlapping_rects_into_their_bounding_box() {
+        let rects = vec![
+            (Vec2::new(0, 0), Vec2::new(10, 10)),
+            (Vec2::new(5, 5), Vec2::new(15, 15)),
+        ];
+
+        let merged = Controller::merge_dirty_rects(rects);
+
+        assert_eq!(merged, vec![(Vec2::new(0, 0), Vec2::new(15, 15))]);
+    }
+
+    #[test]
+    fn merge_dirty_rects_collapses_edge_adjacent_rects() {
+        let rects = vec![
+            (Vec2::new(0, 0), Vec2::new(10, 10)),
+            (Vec2::new(10, 0), Vec2::new(20, 10)),
+        ];
+
+        let merged = Controller::merge_dirty_rects(rects);
+
+        assert_eq!(merged, vec![(Vec2::new(0, 0), Vec2::new(20, 10))]);
+    }
+
+    #[test]
+    fn merge_dirty_rects_leaves_disjoint_rects_apart() {
+        let rects = vec![
+            (Vec2::new(0, 0), Vec2::new(10, 10)),
+            (Vec2::new(20, 20), Vec2::new(30, 30)),
+        ];
+
+        let mut merged = Controller::merge_dirty_rects(rects.clone());
+        merged.sort_by_key(|&(top_left, _)| (top_left.x, top_left.y));
+
+        assert_eq!(merged, rects);
+    }
+
+    #[test]
+    fn merge_dirty_rects_chains_merges_through_an_intermediate_rect() {
+        // Rect 0 and rect 2 don't touch directly, but rect 1 bridges them, so all three must end
+        // up in a single merged rect once rect 0 (or rect 2) is folded into rect 1.
+        let rects = vec![
+            (Vec2::new(0, 0), Vec2::new(10, 10)),
+            (Vec2::new(9, 0), Vec2::new(20, 10)),
+            (Vec2::new(19, 0), Vec2::new(30, 10)),
+        ];
+
+        let merged = Controller::merge_dirty_rects(rects);
+
+        assert_eq!(merged, vec![(Vec2::new(0, 0), Vec2::new(30, 10))]);
+    }
+
+    fn controller_with_layers(count: usize) -> (Controller, Vec<u8>, Vec<Id>) {
+        let mut vram = vec![0u8; 4 * 4 * 4];
+        let mut controller = unsafe {
+            Controller::new(Vec2::new(4, 4), PixelFormat::Bgra8888, vram.as_mut_ptr() as usize)
+        };
+        let ids = (0..count)
+            .map(|_| controller.add_layer(Layer::new(Vec2::new(0, 0), Vec2::new(1, 1))))
+            .collect();
+        (controller, vram, ids)
+    }
+
+    fn order(controller: &Controller) -> Vec<Id> {
+        controller.collection.iter().map(|layer| layer.id).collect()
+    }
+
+    #[test]
+    fn move_to_front_puts_the_layer_last_in_the_collection() {
+        let (mut controller, _vram, ids) = controller_with_layers(3);
+
+        controller.move_to_front(ids[0]).unwrap();
+
+        assert_eq!(order(&controller), vec![ids[1], ids[2], ids[0]]);
+    }
+
+    #[test]
+    fn move_to_back_puts_the_layer_first_in_the_collection() {
+        let (mut controller, _vram, ids) = controller_with_layers(3);
+
+        controller.move_to_back(ids[2]).unwrap();
+
+        assert_eq!(order(&controller), vec![ids[2], ids[0], ids[1]]);
+    }
+
+    #[test]
+    fn raise_swaps_with_the_layer_in_front() {
+        let (mut controller, _vram, ids) = controller_with_layers(3);
+
+        controller.raise(ids[0]).unwrap();
+
+        assert_eq!(order(&controller), vec![ids[1], ids[0], ids[2]]);
+    }
+
+    #[test]
+    fn raise_at_the_front_is_a_no_op() {
+        let (mut controller, _vram, ids) = controller_with_layers(3);
+
+        controller.raise(ids[2]).unwrap();
+
+        assert_eq!(order(&controller), ids);
+    }
+
+    #[test]
+    fn lower_swaps_with_the_layer_behind() {
+        let (mut controller, _vram, ids) = controller_with_layers(3);
+
+        controller.lower(ids[2]).unwrap();
+
+        assert_eq!(order(&controller), vec![ids[0], ids[2], ids[1]]);
+    }
+
+    #[test]
+    fn lower_at_the_back_is_a_no_op() {
+        let (mut controller, _vram, ids) = controller_with_layers(3);
+
+        controller.lower(ids[0]).unwrap();
+
+        assert_eq!(order(&controller), ids);
+    }
+
+    #[test]
+    fn set_z_index_moves_the_layer_to_the_given_position_from_the_back() {
+        let (mut controller, _vram, ids) = controller_with_layers(4);
+
+        // Move the front-most layer (index 3) to z-index 1, counted from the back.
+        controller.set_z_index(ids[3], 1).unwrap();
+
+        assert_eq!(order(&controller), vec![ids[0], ids[3], ids[1], ids[2]]);
+    }
+
+    #[test]
+    fn set_z_index_clamps_to_the_number_of_remaining_layers() {
+        let (mut controller, _vram, ids) = controller_with_layers(3);
+
+        // Only 2 layers remain once `ids[0]` is pulled out, so any z-index >= 2 clamps to the
+        // front (the end of the collection).
+        controller.set_z_index(ids[0], 100).unwrap();
+
+        assert_eq!(order(&controller), vec![ids[1], ids[2], ids[0]]);
+    }
+
+    #[test]
+    fn remove_layer_takes_the_layer_out_and_returns_it() {
+        let (mut controller, _vram, ids) = controller_with_layers(3);
+
+        let removed = controller.remove_layer(ids[1]).unwrap();
+
+        assert_eq!(removed.id, ids[1]);
+        assert_eq!(order(&controller), vec![ids[0], ids[2]]);
+    }
+
+    #[test]
+    fn z_order_methods_report_no_such_layer_for_an_unknown_id() {
+        let (mut controller, _vram, ids) = controller_with_layers(1);
+        let bogus = Layer::new(Vec2::new(0, 0), Vec2::new(1, 1)).id;
+        assert!(!ids.contains(&bogus));
+
+        assert_eq!(controller.move_to_front(bogus), Err(Error::NoSuchLayer(bogus)));
+        assert_eq!(controller.move_to_back(bogus), Err(Error::NoSuchLayer(bogus)));
+        assert_eq!(controller.raise(bogus), Err(Error::NoSuchLayer(bogus)));
+        assert_eq!(controller.lower(bogus), Err(Error::NoSuchLayer(bogus)));
+        assert_eq!(controller.set_z_index(bogus, 0), Err(Error::NoSuchLayer(bogus)));
+        assert_eq!(controller.remove_layer(bogus).err(), Some(Error::NoSuchLayer(bogus)));
+    }
 }